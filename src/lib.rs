@@ -0,0 +1,489 @@
+//! Pure minesweeper game model, independent of any rendering frontend.
+//!
+//! `Field` owns the board state and exposes `click`/`toggle_mark`/`chord` to
+//! mutate it plus `iter` for a renderable view of every cell. None of this
+//! touches a terminal, so it can be driven by any frontend, unit tested
+//! directly, or handed to the `solver` for deduction.
+
+use std::cmp::min;
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+mod solver;
+
+/// Draws a seed with OS entropy, for callers that don't care to reproduce a
+/// specific board.
+pub fn random_seed() -> u64 {
+    thread_rng().gen()
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct IndexPair {
+    pub row: u16,
+    pub col: u16,
+}
+
+pub fn cell_position(size: IndexPair, index: IndexPair) -> usize {
+    (index.row * size.col + index.col) as usize
+}
+
+struct Grid {
+    data: Vec<bool>,
+    size: IndexPair,
+}
+
+impl Grid {
+    fn new(size: IndexPair) -> Self {
+        Self {
+            data: vec![false; (size.row * size.col).into()],
+            size,
+        }
+    }
+
+    fn position(&self, index: IndexPair) -> usize {
+        cell_position(self.size, index)
+    }
+
+    fn get(&self, index: IndexPair) -> bool {
+        let position = self.position(index);
+        self.data[position]
+    }
+
+    fn set(&mut self, index: IndexPair, value: bool) {
+        let position = self.position(index);
+        self.data[position] = value
+    }
+
+    fn sum_neighbors(&self, index: IndexPair) -> u16 {
+        self.around(index)
+            .map(|index| if self.get(index) { 1 } else { 0 })
+            .sum()
+    }
+
+    fn around(&self, index: IndexPair) -> GridIterator {
+        GridIterator::around(self.size, index)
+    }
+
+    fn count(&self) -> u16 {
+        GridIterator::all(self.size)
+            .map(|index| self.get(index) as u16)
+            .sum()
+    }
+}
+
+struct GridIterator {
+    start_index: IndexPair,
+    end_index: IndexPair,
+    current_index: IndexPair,
+}
+
+impl GridIterator {
+    fn new(start_index: IndexPair, end_index: IndexPair) -> Self {
+        Self {
+            start_index,
+            end_index,
+            current_index: start_index,
+        }
+    }
+    fn all(size: IndexPair) -> Self {
+        Self::new(IndexPair { row: 0, col: 0 }, size)
+    }
+
+    fn around(size: IndexPair, index: IndexPair) -> Self {
+        let start_index = IndexPair {
+            row: index.row.saturating_sub(1),
+            col: index.col.saturating_sub(1),
+        };
+        let end_index = IndexPair {
+            row: min(index.row + 2, size.row),
+            col: min(index.col + 2, size.col),
+        };
+        Self::new(start_index, end_index)
+    }
+}
+
+impl Iterator for GridIterator {
+    type Item = IndexPair;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_index.row >= self.end_index.row {
+            return None;
+        }
+        let index = self.current_index;
+        self.current_index.col += 1;
+        if self.current_index.col >= self.end_index.col {
+            self.current_index.col = self.start_index.col;
+            self.current_index.row += 1;
+        }
+        Some(index)
+    }
+}
+
+/// A cell's right-click marking state, cycled `None -> Flagged ->
+/// Questioned -> None` (classic minesweeper's "use query" convention).
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Mark {
+    None,
+    Flagged,
+    Questioned,
+}
+
+impl Mark {
+    fn next(self) -> Mark {
+        match self {
+            Mark::None => Mark::Flagged,
+            Mark::Flagged => Mark::Questioned,
+            Mark::Questioned => Mark::None,
+        }
+    }
+}
+
+struct MarkGrid {
+    data: Vec<Mark>,
+    size: IndexPair,
+}
+
+impl MarkGrid {
+    fn new(size: IndexPair) -> Self {
+        Self {
+            data: vec![Mark::None; (size.row * size.col).into()],
+            size,
+        }
+    }
+
+    fn position(&self, index: IndexPair) -> usize {
+        cell_position(self.size, index)
+    }
+
+    fn get(&self, index: IndexPair) -> Mark {
+        let position = self.position(index);
+        self.data[position]
+    }
+
+    fn set(&mut self, index: IndexPair, value: Mark) {
+        let position = self.position(index);
+        self.data[position] = value;
+    }
+
+    fn count_flagged(&self) -> u16 {
+        GridIterator::all(self.size)
+            .map(|index| (self.get(index) == Mark::Flagged) as u16)
+            .sum()
+    }
+}
+
+/// One recorded player action, in the order it was applied, so a game can be
+/// saved by its seed plus this list and replayed step-by-step. `AutoPlay`
+/// carries no cell, since it can open and flag several at once; replaying it
+/// just re-runs the solver pass, which is deterministic given the board
+/// state built up by the actions before it.
+#[derive(Copy, Clone)]
+pub enum Action {
+    Click(IndexPair),
+    ToggleMark(IndexPair),
+    Chord(IndexPair),
+    AutoPlay,
+}
+
+pub struct Field {
+    size: IndexPair,
+    n_mines: u16,
+    are_mines_allocated: bool,
+    seed: u64,
+    rng: StdRng,
+
+    mines: Grid,
+    opened: Grid,
+    marks: MarkGrid,
+    history: Vec<Action>,
+}
+
+pub struct FieldItem {
+    pub is_opened: bool,
+    pub is_mined: bool,
+    pub mark: Mark,
+}
+
+#[derive(PartialEq)]
+pub enum ClickResult {
+    Safe,
+    Exploded,
+}
+
+impl Field {
+    pub fn new(size: IndexPair, n_mines: u16, seed: u64) -> Self {
+        Self {
+            size,
+            n_mines,
+            are_mines_allocated: false,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+
+            mines: Grid::new(size),
+            opened: Grid::new(size),
+            marks: MarkGrid::new(size),
+            history: Vec::new(),
+        }
+    }
+
+    /// Replays a previously recorded game from scratch: same seed, same
+    /// board, same sequence of actions, same result.
+    pub fn replay(size: IndexPair, n_mines: u16, seed: u64, history: &[Action]) -> Self {
+        let mut field = Field::new(size, n_mines, seed);
+        for &action in history {
+            match action {
+                Action::Click(index) => {
+                    field.click(index);
+                }
+                Action::ToggleMark(index) => {
+                    field.toggle_mark(index);
+                }
+                Action::Chord(index) => {
+                    field.chord(index);
+                }
+                Action::AutoPlay => {
+                    field.auto_play();
+                }
+            }
+        }
+        field
+    }
+
+    pub fn size(&self) -> IndexPair {
+        self.size
+    }
+
+    pub fn n_mines(&self) -> u16 {
+        self.n_mines
+    }
+
+    pub fn n_opened(&self) -> u16 {
+        self.opened.count()
+    }
+
+    pub fn flagged_count(&self) -> u16 {
+        self.marks.count_flagged()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn history(&self) -> &[Action] {
+        &self.history
+    }
+
+    fn allocate_mines(&mut self, starting_index: IndexPair) {
+        let excluded_indices: HashSet<IndexPair> =
+            HashSet::from_iter(self.mines.around(starting_index));
+        let mut indices: Vec<_> = GridIterator::all(self.size)
+            .filter(|x| !excluded_indices.contains(x))
+            .collect();
+
+        indices.shuffle(&mut self.rng);
+
+        for index in &indices[..self.n_mines as usize] {
+            self.mines.set(*index, true);
+        }
+        self.are_mines_allocated = true;
+    }
+
+    pub fn click(&mut self, index: IndexPair) -> ClickResult {
+        self.history.push(Action::Click(index));
+        if !self.are_mines_allocated {
+            self.allocate_mines(index);
+        }
+        if self.marks.get(index) != Mark::Flagged {
+            self.open_at(index);
+            if !self.mines.get(index) {
+                return ClickResult::Safe;
+            } else {
+                return ClickResult::Exploded;
+            }
+        }
+        ClickResult::Safe
+    }
+
+    pub fn toggle_mark(&mut self, index: IndexPair) -> ClickResult {
+        self.history.push(Action::ToggleMark(index));
+        if !self.opened.get(index) {
+            self.marks.set(index, self.marks.get(index).next());
+        }
+        ClickResult::Safe
+    }
+
+    fn open_at(&mut self, index: IndexPair) {
+        if self.opened.get(index) {
+            return;
+        }
+        self.opened.set(index, true);
+        self.marks.set(index, Mark::None);
+        if self.mines.get(index) || self.mines.sum_neighbors(index) > 0 {
+            return;
+        }
+        for index in self.opened.around(index) {
+            if !self.opened.get(index) && !self.mines.get(index) {
+                self.open_at(index);
+            }
+        }
+    }
+
+    fn around(&self, index: IndexPair) -> GridIterator {
+        self.opened.around(index)
+    }
+
+    fn is_opened(&self, index: IndexPair) -> bool {
+        self.opened.get(index)
+    }
+
+    fn is_flagged(&self, index: IndexPair) -> bool {
+        self.marks.get(index) == Mark::Flagged
+    }
+
+    pub fn neighbor_mine_count(&self, index: IndexPair) -> u16 {
+        self.mines.sum_neighbors(index)
+    }
+
+    pub fn hint(&self) -> Option<IndexPair> {
+        solver::deduce(self).safe.into_iter().next()
+    }
+
+    /// Applies one round of solver deductions: flags guaranteed mines and
+    /// opens guaranteed-safe cells. Returns `false` if the solver could not
+    /// deduce anything, meaning the player would have to guess.
+    pub fn auto_play(&mut self) -> bool {
+        self.history.push(Action::AutoPlay);
+        let deduction = solver::deduce(self);
+        if deduction.requires_guess() {
+            return false;
+        }
+        for index in &deduction.mines {
+            self.marks.set(*index, Mark::Flagged);
+        }
+        for index in deduction.safe {
+            self.open_at(index);
+        }
+        true
+    }
+
+    /// Chords an already-opened numbered cell: if its flagged-neighbor count
+    /// matches its displayed number, opens every remaining unflagged
+    /// neighbor at once. Mis-flagging a mine surfaces as `Exploded`, same as
+    /// a direct click on it would.
+    ///
+    /// The frontend only binds this to middle-click, not left+right held
+    /// together: crossterm reports left and right mouse buttons as
+    /// independent `Down`/`Up` events, and by the time a second button's
+    /// `Down` would reveal that both are held, the first button's own
+    /// click/flag has already fired. Supporting the combo would mean
+    /// deferring every click until release and unwinding whichever
+    /// single-button action already ran, which isn't worth it for a
+    /// convenience alias of the same gesture.
+    pub fn chord(&mut self, index: IndexPair) -> ClickResult {
+        self.history.push(Action::Chord(index));
+        if !self.opened.get(index) || self.mines.get(index) {
+            return ClickResult::Safe;
+        }
+        let flagged_neighbors = self.around(index).filter(|&n| self.is_flagged(n)).count() as u16;
+        if flagged_neighbors != self.mines.sum_neighbors(index) {
+            return ClickResult::Safe;
+        }
+
+        let mut result = ClickResult::Safe;
+        for neighbor in self.opened.around(index) {
+            if self.is_flagged(neighbor) || self.opened.get(neighbor) {
+                continue;
+            }
+            self.open_at(neighbor);
+            if self.mines.get(neighbor) {
+                result = ClickResult::Exploded;
+            }
+        }
+        result
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (IndexPair, FieldItem)> + '_ {
+        let iterator = GridIterator::all(self.size);
+        iterator.map(|index| {
+            (
+                index,
+                FieldItem {
+                    is_opened: self.opened.get(index),
+                    is_mined: self.mines.get(index),
+                    mark: self.marks.get(index),
+                },
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flood_fill_never_opens_a_mine_but_still_reveals_its_numbered_neighbors() {
+        let size = IndexPair { row: 5, col: 5 };
+        let mut field = Field::new(size, 1, 3);
+
+        let result = field.click(IndexPair { row: 0, col: 4 });
+
+        assert!(result == ClickResult::Safe);
+        assert_eq!(field.n_opened(), 23);
+        assert!(!field.opened.get(IndexPair { row: 0, col: 1 }));
+    }
+
+    #[test]
+    fn allocate_mines_places_exactly_n_mines_and_excludes_the_starting_cell() {
+        let size = IndexPair { row: 6, col: 6 };
+        for seed in 0..20 {
+            let mut field = Field::new(size, 6, seed);
+            let starting_index = IndexPair { row: 2, col: 2 };
+
+            field.click(starting_index);
+
+            let n_mines = field.iter().filter(|(_, item)| item.is_mined).count();
+            assert_eq!(n_mines, 6);
+            assert!(!field.mines.get(starting_index));
+        }
+    }
+
+    #[test]
+    fn opening_every_safe_cell_satisfies_the_win_condition() {
+        let size = IndexPair { row: 5, col: 5 };
+        let mut field = Field::new(size, 1, 3);
+        field.click(IndexPair { row: 0, col: 4 });
+
+        let safe_cells: Vec<IndexPair> = field
+            .iter()
+            .filter(|(_, item)| !item.is_mined)
+            .map(|(index, _)| index)
+            .collect();
+        for index in safe_cells {
+            field.click(index);
+        }
+
+        let n_total = size.row * size.col;
+        assert_eq!(field.n_mines(), n_total - field.n_opened());
+    }
+
+    #[test]
+    fn clicking_a_mine_reports_exploded_without_opening_the_whole_board() {
+        let size = IndexPair { row: 5, col: 5 };
+        let mut field = Field::new(size, 1, 3);
+        field.click(IndexPair { row: 0, col: 4 });
+        let mine_index = field
+            .iter()
+            .find(|(_, item)| item.is_mined)
+            .map(|(index, _)| index)
+            .unwrap();
+
+        let result = field.click(mine_index);
+
+        assert!(result == ClickResult::Exploded);
+        assert!(field.opened.get(mine_index));
+    }
+}
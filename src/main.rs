@@ -1,9 +1,6 @@
 //! Minesweeper game
 
-use std::cmp::min;
-use std::collections::HashSet;
 use std::io::{stdout, ErrorKind, Write};
-use std::iter::FromIterator;
 
 use crossterm::cursor::{Hide, MoveTo, Show};
 use crossterm::event::{
@@ -18,208 +15,204 @@ use crossterm::{
     execute, queue,
     style::{Color, PrintStyledContent, ResetColor, Stylize},
 };
-use rand::prelude::*;
 
-#[derive(Copy, Clone, Eq, Hash, PartialEq)]
-struct IndexPair {
-    row: u16,
-    col: u16,
-}
+use mines::{cell_position, Action, ClickResult, Field, FieldItem, IndexPair, Mark};
 
-struct Grid {
-    data: Vec<bool>,
+struct Options {
     size: IndexPair,
+    n_mines: u16,
+    seed: Option<u64>,
+    save_path: Option<String>,
+    replay_path: Option<String>,
 }
 
-impl Grid {
-    fn new(size: IndexPair) -> Self {
-        Self {
-            data: vec![false; (size.row * size.col).into()],
-            size,
+impl Options {
+    const BEGINNER: Options = Options {
+        size: IndexPair { row: 8, col: 8 },
+        n_mines: 10,
+        seed: None,
+        save_path: None,
+        replay_path: None,
+    };
+    const INTERMEDIATE: Options = Options {
+        size: IndexPair { row: 16, col: 16 },
+        n_mines: 40,
+        seed: None,
+        save_path: None,
+        replay_path: None,
+    };
+    const EXPERT: Options = Options {
+        size: IndexPair { row: 16, col: 30 },
+        n_mines: 99,
+        seed: None,
+        save_path: None,
+        replay_path: None,
+    };
+
+    /// Parses CLI flags of the form `--beginner`/`--intermediate`/`--expert`,
+    /// `--rows`/`--cols`/`--mines <n>`, `--seed <n>`, applied in order over
+    /// the beginner preset, plus `--save <path>` to record the game and
+    /// `--replay <path>` to reconstruct one previously recorded (which
+    /// overrides the board shape and seed instead of them). Returns a
+    /// description of the problem on bad input.
+    fn from_args(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::BEGINNER;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--beginner" => options = Options::BEGINNER,
+                "--intermediate" => options = Options::INTERMEDIATE,
+                "--expert" => options = Options::EXPERT,
+                "--rows" => options.size.row = Self::parse_value(&mut args, arg)?,
+                "--cols" => options.size.col = Self::parse_value(&mut args, arg)?,
+                "--mines" => options.n_mines = Self::parse_value(&mut args, arg)?,
+                "--seed" => options.seed = Some(Self::parse_value(&mut args, arg)?),
+                "--save" => options.save_path = Some(Self::parse_value(&mut args, arg)?),
+                "--replay" => options.replay_path = Some(Self::parse_value(&mut args, arg)?),
+                _ => return Err(format!("unrecognized argument: {arg}")),
+            }
         }
+        options.validate()?;
+        Ok(options)
     }
 
-    fn position(&self, index: IndexPair) -> usize {
-        (index.row * self.size.col + index.col) as usize
+    fn parse_value<'a, T: std::str::FromStr>(
+        args: &mut impl Iterator<Item = &'a String>,
+        flag: &str,
+    ) -> Result<T, String> {
+        args.next()
+            .ok_or_else(|| format!("{flag} expects a value"))?
+            .parse()
+            .map_err(|_| format!("{flag} expects a number"))
     }
 
-    fn get(&self, index: IndexPair) -> bool {
-        let position = self.position(index);
-        self.data[position]
+    fn validate(&self) -> Result<(), String> {
+        validate_board(self.size, self.n_mines)
     }
+}
 
-    fn set(&mut self, index: IndexPair, value: bool) {
-        let position = self.position(index);
-        self.data[position] = value
+/// `allocate_mines` excludes the (up to 3x3) neighborhood of the first click
+/// before shuffling, so there must be enough non-excluded cells left for
+/// `n_mines` regardless of where that click lands.
+///
+/// The cell count and doubled column count also have to fit in a `u16`,
+/// since `GameState` and `Field` both compute them with `u16` arithmetic.
+/// Shared between the CLI flags path (`Options::validate`) and `load_replay`,
+/// since a hand-edited or corrupt replay file can describe the same kind of
+/// invalid board a CLI invocation can.
+fn validate_board(size: IndexPair, n_mines: u16) -> Result<(), String> {
+    if size.row == 0 || size.col == 0 {
+        return Err("board dimensions must be at least 1x1".to_string());
     }
-
-    fn sum_neighbors(&self, index: IndexPair) -> u16 {
-        self.around(index)
-            .map(|index| if self.get(index) { 1 } else { 0 })
-            .sum()
+    if size.col as u32 * 2 > u16::MAX as u32 {
+        return Err(format!("{} columns is too wide for a board", size.col));
     }
-
-    fn around(&self, index: IndexPair) -> GridIterator {
-        GridIterator::around(self.size, index)
+    let n_cells = size.row as u32 * size.col as u32;
+    if n_cells > u16::MAX as u32 {
+        return Err(format!("{}x{} board has too many cells", size.row, size.col));
     }
-
-    fn count(&self) -> u16 {
-        GridIterator::all(self.size)
-            .map(|index| self.get(index) as u16)
-            .sum()
+    if n_mines as u32 + 9 >= n_cells {
+        return Err(format!(
+            "{n_mines} mines is too many for a {}x{} board",
+            size.row, size.col
+        ));
     }
+    Ok(())
 }
 
-struct GridIterator {
-    start_index: IndexPair,
-    end_index: IndexPair,
-    current_index: IndexPair,
-}
-
-impl GridIterator {
-    fn new(start_index: IndexPair, end_index: IndexPair) -> Self {
-        Self {
-            start_index,
-            end_index,
-            current_index: start_index,
-        }
-    }
-    fn all(size: IndexPair) -> Self {
-        Self::new(IndexPair { row: 0, col: 0 }, size)
-    }
-
-    fn around(size: IndexPair, index: IndexPair) -> Self {
-        let start_index = IndexPair {
-            row: index.row.saturating_sub(1),
-            col: index.col.saturating_sub(1),
-        };
-        let end_index = IndexPair {
-            row: min(index.row + 2, size.row),
-            col: min(index.col + 2, size.col),
-        };
-        Self::new(start_index, end_index)
+/// `AutoPlay` carries no cell of its own; `0 0` is written in its place and
+/// ignored on load.
+fn action_code_and_index(action: Action) -> (u8, IndexPair) {
+    match action {
+        Action::Click(index) => (0, index),
+        Action::ToggleMark(index) => (1, index),
+        Action::Chord(index) => (2, index),
+        Action::AutoPlay => (3, IndexPair { row: 0, col: 0 }),
     }
 }
 
-impl Iterator for GridIterator {
-    type Item = IndexPair;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current_index.row >= self.end_index.row {
-            return None;
-        }
-        let index = self.current_index;
-        self.current_index.col += 1;
-        if self.current_index.col >= self.end_index.col {
-            self.current_index.col = self.start_index.col;
-            self.current_index.row += 1;
-        }
-        Some(index)
+fn action_from_code(code: u8, index: IndexPair) -> Result<Action, String> {
+    match code {
+        0 => Ok(Action::Click(index)),
+        1 => Ok(Action::ToggleMark(index)),
+        2 => Ok(Action::Chord(index)),
+        3 => Ok(Action::AutoPlay),
+        _ => Err(format!("unknown action code: {code}")),
     }
 }
 
-struct Field {
-    size: IndexPair,
-    n_mines: u16,
-    are_mines_allocated: bool,
-
-    mines: Grid,
-    opened: Grid,
-    flags: Grid,
-}
-
-struct FieldItem {
-    is_opened: bool,
-    is_mined: bool,
-    is_flagged: bool,
-}
-
-#[derive(PartialEq)]
-enum ClickResult {
-    Safe,
-    Exploded,
-}
-
-impl Field {
-    fn new(size: IndexPair, n_mines: u16) -> Self {
-        Self {
-            size,
-            n_mines,
-            are_mines_allocated: false,
-
-            mines: Grid::new(size),
-            opened: Grid::new(size),
-            flags: Grid::new(size),
-        }
-    }
-
-    fn allocate_mines(&mut self, starting_index: IndexPair) {
-        let excluded_indices: HashSet<IndexPair> =
-            HashSet::from_iter(self.mines.around(starting_index));
-        let mut indices: Vec<_> = GridIterator::all(self.size)
-            .filter(|x| !excluded_indices.contains(x))
-            .collect();
-
-        let mut rng = thread_rng();
-        indices.shuffle(&mut rng);
-
-        for index in &indices[..self.n_mines as usize] {
-            self.mines.set(*index, true);
-        }
-        self.are_mines_allocated = true;
-    }
-
-    fn handle_click(&mut self, index: IndexPair) -> ClickResult {
-        if !self.are_mines_allocated {
-            self.allocate_mines(index);
-        }
-        if !self.flags.get(index) {
-            self.open_at(index);
-            if !self.mines.get(index) {
-                return ClickResult::Safe;
-            } else {
-                return ClickResult::Exploded;
-            }
-        }
-        ClickResult::Safe
-    }
-
-    fn handle_force_click(&mut self, index: IndexPair) -> ClickResult {
-        if !self.opened.get(index) {
-            self.flags.set(index, !self.flags.get(index));
-        }
-        ClickResult::Safe
+/// Saves a field's seed and full action history as plain text, so it can
+/// later be reconstructed by `load_replay`.
+fn save_replay(path: &str, field: &Field) -> std::io::Result<()> {
+    let size = field.size();
+    let mut contents = format!(
+        "{} {} {} {}\n",
+        size.row,
+        size.col,
+        field.n_mines(),
+        field.seed()
+    );
+    for &action in field.history() {
+        let (code, index) = action_code_and_index(action);
+        contents.push_str(&format!("{} {} {}\n", index.row, index.col, code));
     }
+    std::fs::write(path, contents)
+}
 
-    fn open_at(&mut self, index: IndexPair) {
-        if self.opened.get(index) {
-            return;
-        }
-        self.opened.set(index, true);
-        self.flags.set(index, false);
-        if self.mines.get(index) || self.mines.sum_neighbors(index) > 0 {
-            return;
-        }
-        for index in self.opened.around(index) {
-            if !self.opened.get(index) && !self.mines.get(index) {
-                self.open_at(index);
-            }
+/// Loads a game previously written by `save_replay` and replays it back to
+/// its final state.
+fn load_replay(path: &str) -> std::io::Result<Field> {
+    let invalid = |message: String| std::io::Error::new(ErrorKind::InvalidData, message);
+
+    let contents = std::fs::read_to_string(path)?;
+    let mut lines = contents.lines();
+
+    let header: Vec<&str> = lines
+        .next()
+        .ok_or_else(|| invalid("replay file is empty".to_string()))?
+        .split_whitespace()
+        .collect();
+    let [rows, cols, n_mines, seed] = header[..] else {
+        return Err(invalid("replay header must have 4 fields".to_string()));
+    };
+    let size = IndexPair {
+        row: rows
+            .parse()
+            .map_err(|_| invalid("invalid row count".to_string()))?,
+        col: cols
+            .parse()
+            .map_err(|_| invalid("invalid column count".to_string()))?,
+    };
+    let n_mines: u16 = n_mines
+        .parse()
+        .map_err(|_| invalid("invalid mine count".to_string()))?;
+    let seed: u64 = seed.parse().map_err(|_| invalid("invalid seed".to_string()))?;
+    validate_board(size, n_mines).map_err(invalid)?;
+
+    let mut history = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [row, col, code] = fields[..] else {
+            return Err(invalid("replay action line must have 3 fields".to_string()));
+        };
+        let index = IndexPair {
+            row: row
+                .parse()
+                .map_err(|_| invalid("invalid action row".to_string()))?,
+            col: col
+                .parse()
+                .map_err(|_| invalid("invalid action column".to_string()))?,
+        };
+        if index.row >= size.row || index.col >= size.col {
+            return Err(invalid(format!("action at {index:?} is outside the board")));
         }
+        let code: u8 = code
+            .parse()
+            .map_err(|_| invalid("invalid action code".to_string()))?;
+        history.push(action_from_code(code, index).map_err(invalid)?);
     }
 
-    fn iter(&self) -> impl Iterator<Item = (IndexPair, FieldItem)> + '_ {
-        let iterator = GridIterator::all(self.size);
-        iterator.map(|index| {
-            (
-                index,
-                FieldItem {
-                    is_opened: self.opened.get(index),
-                    is_mined: self.mines.get(index),
-                    is_flagged: self.flags.get(index),
-                },
-            )
-        })
-    }
+    Ok(Field::replay(size, n_mines, seed, &history))
 }
 
 #[derive(PartialEq)]
@@ -229,21 +222,89 @@ enum GameStatus {
     Loss,
 }
 
+/// A cell's rendered appearance as of the last drawn frame, so `draw_field`
+/// can skip cells whose appearance hasn't changed since then.
+#[derive(Clone, Copy, PartialEq)]
+struct CellSnapshot {
+    is_opened: bool,
+    mark: Mark,
+    is_mined: bool,
+    neighbors: u16,
+    bg_color: Color,
+}
+
 struct GameState {
     field: Field,
     stdout: std::io::Stdout,
     start: IndexPair,
     status: GameStatus,
+    hint: Option<IndexPair>,
+    cursor: IndexPair,
+    front_buffer: Vec<Option<CellSnapshot>>,
+    back_buffer: Vec<Option<CellSnapshot>>,
 }
 
 impl GameState {
-    fn new(size: IndexPair, n_mines: u16) -> Self {
-        Self {
-            field: Field::new(size, n_mines),
+    fn new(field: Field) -> std::io::Result<Self> {
+        let (term_cols, term_rows) = crossterm::terminal::size()?;
+        let size = field.size();
+        let field_width = size.col * 2;
+        let start = IndexPair {
+            row: 1 + term_rows.saturating_sub(1 + size.row) / 2,
+            col: term_cols.saturating_sub(field_width) / 2,
+        };
+        let n_cells = size.row as usize * size.col as usize;
+        Ok(Self {
+            field,
             stdout: stdout(),
-            start: IndexPair { row: 1, col: 1 },
+            start,
             status: GameStatus::InProgress,
+            hint: None,
+            cursor: IndexPair { row: 0, col: 0 },
+            front_buffer: vec![None; n_cells],
+            back_buffer: vec![None; n_cells],
+        })
+    }
+
+    /// Moves the keyboard cursor by one cell, clamped to the board.
+    fn move_cursor(&mut self, drow: i32, dcol: i32) {
+        let size = self.field.size();
+        let row = (self.cursor.row as i32 + drow).clamp(0, size.row as i32 - 1);
+        let col = (self.cursor.col as i32 + dcol).clamp(0, size.col as i32 - 1);
+        self.cursor = IndexPair {
+            row: row as u16,
+            col: col as u16,
+        };
+    }
+
+    fn handle_key(&mut self, event: &KeyEvent) -> std::io::Result<()> {
+        if self.status != GameStatus::InProgress {
+            return Ok(());
         }
+        match event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1, 0),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1, 0),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(0, -1),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(0, 1),
+            KeyCode::Char(' ') => {
+                self.hint = None;
+                let result = self.field.click(self.cursor);
+                self.after_click(result);
+            }
+            KeyCode::Char('f') => {
+                self.hint = None;
+                self.field.toggle_mark(self.cursor);
+            }
+            KeyCode::Char('i') => self.hint = self.field.hint(),
+            KeyCode::Char('a') => {
+                self.hint = None;
+                if self.field.auto_play() && self.check_for_win() {
+                    self.win_game();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
     }
 
     fn handle_mouse(&mut self, event: &MouseEvent) -> std::io::Result<()> {
@@ -257,6 +318,7 @@ impl GameState {
         let Some(index) = self.convert_absolute_to_relative(mouse_index) else {
             return Ok(());
         };
+        self.hint = None;
         let MouseEvent {
             kind: MouseEventKind::Down(button),
             modifiers,
@@ -266,18 +328,25 @@ impl GameState {
             return Ok(());
         };
         let click_result = match (*button, *modifiers) {
-            (MouseButton::Left, KeyModifiers::NONE) => self.field.handle_click(index),
-            (MouseButton::Left, KeyModifiers::SHIFT) => self.field.handle_force_click(index),
-            (MouseButton::Right, KeyModifiers::NONE) => self.field.handle_force_click(index),
+            (MouseButton::Left, KeyModifiers::NONE) => self.field.click(index),
+            (MouseButton::Left, KeyModifiers::SHIFT) => self.field.toggle_mark(index),
+            (MouseButton::Right, KeyModifiers::NONE) => self.field.toggle_mark(index),
+            (MouseButton::Middle, KeyModifiers::NONE) => self.field.chord(index),
             _ => ClickResult::Safe,
         };
+        self.after_click(click_result);
+        Ok(())
+    }
+
+    /// Shared win/loss bookkeeping for both the mouse and keyboard input
+    /// paths, so they stay consistent no matter which one drove a click.
+    fn after_click(&mut self, click_result: ClickResult) {
         if click_result == ClickResult::Exploded {
             self.lose_game();
         }
         if self.check_for_win() {
             self.win_game();
         }
-        Ok(())
     }
 
     fn lose_game(&mut self) {
@@ -286,10 +355,10 @@ impl GameState {
     }
 
     fn check_for_win(&self) -> bool {
-        let n_opened = self.field.opened.count();
-        let n_total = self.field.size.row * self.field.size.col;
+        let n_opened = self.field.n_opened();
+        let n_total = self.field.size().row * self.field.size().col;
 
-        self.field.n_mines == (n_total - n_opened)
+        self.field.n_mines() == (n_total - n_opened)
     }
 
     fn win_game(&mut self) {
@@ -303,27 +372,49 @@ impl GameState {
         let grey_opened = Color::AnsiValue(253);
         let white_closed = Color::AnsiValue(48);
         let grey_closed = Color::AnsiValue(41);
+        let hint_color = Color::AnsiValue(226);
+        let cursor_color = Color::AnsiValue(245);
 
         for (
             index,
             FieldItem {
                 is_opened,
                 is_mined,
-                is_flagged,
+                mark,
             },
         ) in self.field.iter()
         {
-            let bg_color = match (is_opened, (index.col + index.row) % 2) {
-                (true, 0) => grey_opened,
-                (true, 1) => white_opened,
-                (false, 0) => grey_closed,
-                (false, 1) => white_closed,
-                _ => unreachable!(),
+            let bg_color = if self.cursor == index {
+                cursor_color
+            } else if !is_opened && self.hint == Some(index) {
+                hint_color
+            } else {
+                match (is_opened, (index.col + index.row) % 2) {
+                    (true, 0) => grey_opened,
+                    (true, 1) => white_opened,
+                    (false, 0) => grey_closed,
+                    (false, 1) => white_closed,
+                    _ => unreachable!(),
+                }
+            };
+            let neighbors = self.field.neighbor_mine_count(index);
+            let snapshot = CellSnapshot {
+                is_opened,
+                mark,
+                is_mined,
+                neighbors,
+                bg_color,
             };
-            let neighbors = self.field.mines.sum_neighbors(index);
-            let content = match (is_opened, is_flagged, is_mined, neighbors) {
-                (false, false, ..) => "  ".to_string().with(bg_color),
-                (false, true, ..) => " P".to_string().with(red),
+            let position = cell_position(self.field.size(), index);
+            self.back_buffer[position] = Some(snapshot);
+            if self.front_buffer[position] == Some(snapshot) {
+                continue;
+            }
+
+            let content = match (is_opened, mark, is_mined, neighbors) {
+                (false, Mark::None, ..) => "  ".to_string().with(bg_color),
+                (false, Mark::Flagged, ..) => " P".to_string().with(red),
+                (false, Mark::Questioned, ..) => " ?".to_string().with(white_closed),
                 (true, _, true, ..) => " *".to_string().with(red),
                 (true, _, false, 0) => "  ".to_string().with(bg_color),
                 (true, _, false, ..) => format!(" {}", neighbors).with(blue),
@@ -335,6 +426,7 @@ impl GameState {
             )?;
         }
         queue!(self.stdout, ResetColor)?;
+        std::mem::swap(&mut self.front_buffer, &mut self.back_buffer);
         Ok(())
     }
 
@@ -345,8 +437,9 @@ impl GameState {
 
         let status_line = match self.status {
             GameStatus::InProgress => format!(
-                "Flags: {:03}",
-                self.field.n_mines as i32 - self.field.flags.count() as i32
+                "Seed: {} | Flags: {:03}",
+                self.field.seed(),
+                self.field.n_mines() as i32 - self.field.flagged_count() as i32
             )
             .with(white),
             GameStatus::Win => String::from("You won!").with(green),
@@ -378,7 +471,7 @@ impl GameState {
                 row: old_coords.row - self.start.row,
                 col: (old_coords.col - self.start.col) / 2,
             };
-            if new_coords.row < self.field.size.row && new_coords.col < self.field.size.col {
+            if new_coords.row < self.field.size().row && new_coords.col < self.field.size().col {
                 return Some(new_coords);
             }
         }
@@ -392,11 +485,24 @@ fn main() -> std::io::Result<()> {
         return Err(std::io::Error::new(ErrorKind::Other, "not a tty!"));
     }
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options::from_args(&args)
+        .map_err(|message| std::io::Error::new(ErrorKind::InvalidInput, message))?;
+
+    let field = match &options.replay_path {
+        Some(path) => load_replay(path)?,
+        None => Field::new(
+            options.size,
+            options.n_mines,
+            options.seed.unwrap_or_else(mines::random_seed),
+        ),
+    };
+
     // setup terminal
     enable_raw_mode()?;
     execute!(stdout(), EnableMouseCapture, EnterAlternateScreen, Hide)?;
 
-    let mut game = GameState::new(IndexPair { row: 10, col: 10 }, 10);
+    let mut game = GameState::new(field)?;
 
     // event loop
     loop {
@@ -409,6 +515,7 @@ fn main() -> std::io::Result<()> {
                 ..
             }) => break,
             Event::Mouse(event) => game.handle_mouse(&event),
+            Event::Key(event) => game.handle_key(&event),
             _ => continue,
         }?;
     }
@@ -417,5 +524,9 @@ fn main() -> std::io::Result<()> {
     disable_raw_mode()?;
     execute!(stdout(), DisableMouseCapture, LeaveAlternateScreen, Show)?;
 
+    if let Some(path) = &options.save_path {
+        save_replay(path, &game.field)?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,183 @@
+//! Constraint-based deduction over an opened `Field`.
+//!
+//! Every opened numbered cell gives a constraint over its unknown (unopened,
+//! unflagged) neighbors: the number of mines among them equals the displayed
+//! count minus the number of already-flagged neighbors. Single-point rules
+//! (a constraint of value 0 is all-safe, a constraint whose value equals its
+//! size is all-mines) are applied together with subset subtraction - when
+//! one constraint's cells are a subset of another's, the difference forms a
+//! new constraint on the remaining cells - iterated to a fixpoint.
+
+use std::collections::HashSet;
+
+use crate::{Field, IndexPair};
+
+struct Constraint {
+    cells: HashSet<IndexPair>,
+    mines: u16,
+}
+
+/// The cells a `deduce` pass was able to classify with certainty.
+#[derive(Default)]
+pub struct Deduction {
+    pub safe: HashSet<IndexPair>,
+    pub mines: HashSet<IndexPair>,
+}
+
+impl Deduction {
+    pub fn requires_guess(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+}
+
+fn build_constraints(field: &Field) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+    for (index, item) in field.iter() {
+        if !item.is_opened || item.is_mined {
+            continue;
+        }
+        let mut cells = HashSet::new();
+        let mut flagged = 0u16;
+        for neighbor in field.around(index) {
+            if field.is_flagged(neighbor) {
+                flagged += 1;
+            } else if !field.is_opened(neighbor) {
+                cells.insert(neighbor);
+            }
+        }
+        if cells.is_empty() {
+            continue;
+        }
+        constraints.push(Constraint {
+            cells,
+            mines: field.neighbor_mine_count(index).saturating_sub(flagged),
+        });
+    }
+    constraints
+}
+
+pub fn deduce(field: &Field) -> Deduction {
+    let base_constraints = build_constraints(field);
+    let mut deduction = Deduction::default();
+
+    loop {
+        let mut live_constraints: Vec<Constraint> = Vec::new();
+        for constraint in &base_constraints {
+            let cells: HashSet<IndexPair> = constraint
+                .cells
+                .iter()
+                .copied()
+                .filter(|cell| !deduction.safe.contains(cell) && !deduction.mines.contains(cell))
+                .collect();
+            if cells.is_empty() {
+                continue;
+            }
+            let known_mines = constraint
+                .cells
+                .iter()
+                .filter(|cell| deduction.mines.contains(cell))
+                .count() as u16;
+            live_constraints.push(Constraint {
+                cells,
+                mines: constraint.mines.saturating_sub(known_mines),
+            });
+        }
+
+        let mut derived = Vec::new();
+        for a in &live_constraints {
+            for b in &live_constraints {
+                if a.cells.len() < b.cells.len() && a.cells.is_subset(&b.cells) {
+                    derived.push(Constraint {
+                        cells: b.cells.difference(&a.cells).copied().collect(),
+                        mines: b.mines.saturating_sub(a.mines),
+                    });
+                }
+            }
+        }
+        live_constraints.extend(derived);
+
+        let mut progressed = false;
+        for constraint in &live_constraints {
+            if constraint.mines == 0 {
+                for &cell in &constraint.cells {
+                    progressed |= deduction.safe.insert(cell);
+                }
+            } else if constraint.mines as usize == constraint.cells.len() {
+                for &cell in &constraint.cells {
+                    progressed |= deduction.mines.insert(cell);
+                }
+            }
+        }
+
+        if !progressed {
+            break;
+        }
+    }
+
+    deduction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Field;
+
+    #[test]
+    fn single_point_rule_flags_the_only_remaining_cell() {
+        let size = IndexPair { row: 4, col: 4 };
+        let mut field = Field::new(size, 1, 0);
+        field.click(IndexPair { row: 1, col: 1 });
+
+        let deduction = deduce(&field);
+
+        assert!(deduction.safe.is_empty());
+        assert_eq!(
+            deduction.mines,
+            HashSet::from([IndexPair { row: 3, col: 0 }])
+        );
+    }
+
+    /// Regression test for a board where single-point rules alone only flag
+    /// the three mines around the opened (5,0) corner; every safe cell and
+    /// the remaining mines come from subtracting one constraint from a
+    /// superset constraint.
+    #[test]
+    fn subset_subtraction_finds_deductions_single_point_rules_miss() {
+        let size = IndexPair { row: 6, col: 6 };
+        let mut field = Field::new(size, 6, 19986);
+        for index in [
+            IndexPair { row: 0, col: 0 },
+            IndexPair { row: 0, col: 5 },
+            IndexPair { row: 5, col: 0 },
+            IndexPair { row: 5, col: 5 },
+            IndexPair { row: 2, col: 2 },
+            IndexPair { row: 3, col: 3 },
+        ] {
+            field.click(index);
+        }
+
+        let deduction = deduce(&field);
+
+        assert_eq!(
+            deduction.safe,
+            HashSet::from([
+                IndexPair { row: 0, col: 3 },
+                IndexPair { row: 2, col: 3 },
+                IndexPair { row: 3, col: 1 },
+                IndexPair { row: 3, col: 2 },
+                IndexPair { row: 4, col: 3 },
+                IndexPair { row: 5, col: 4 },
+            ])
+        );
+        assert_eq!(
+            deduction.mines,
+            HashSet::from([
+                IndexPair { row: 1, col: 3 },
+                IndexPair { row: 3, col: 0 },
+                IndexPair { row: 4, col: 0 },
+                IndexPair { row: 4, col: 1 },
+                IndexPair { row: 5, col: 1 },
+            ])
+        );
+    }
+}